@@ -4,9 +4,9 @@
 extern crate panic_halt;
 extern crate metro_m4 as hal;
 
-use embedded_graphics::image::Image16BPP;
+use embedded_graphics::image::{Image, ImageRaw};
+use embedded_graphics::pixelcolor::Rgb565;
 use embedded_graphics::prelude::*;
-use embedded_graphics::primitives::rectangle::Rectangle;
 
 use hal::spi_master;
 use hal::prelude::*;
@@ -50,13 +50,14 @@ fn main() -> ! {
     // My particular lcd seems to be off a few pixels
     disp.set_offset(1, 25);
 
-    let black_backdrop = Rectangle::new(Coord::new(0, 0), Coord::new(160, 80)).fill(Some(0x0000u16.into()));
+    disp.clear(Rgb565::BLACK).unwrap();
 
-    disp.draw(black_backdrop.into_iter());
-    
-    let ferris = Image16BPP::new(include_bytes!("./ferris.raw"), 86, 64).translate(Coord::new(40, 33));
-    
-    disp.draw(ferris.into_iter());
+    // Centered on the 160x80 landscape panel (image is 86x64); the old
+    // (40, 33) placement ran 17 rows past the bottom edge.
+    let ferris_raw = ImageRaw::<Rgb565>::new(include_bytes!("./ferris.raw"), 86);
+    let ferris = Image::new(&ferris_raw, Point::new(37, 8));
+
+    ferris.draw(&mut disp).unwrap();
 
     loop {}
 }
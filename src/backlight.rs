@@ -0,0 +1,36 @@
+//! Optional backlight pin control. `()` is a no-op default for panels
+//! wired without a dedicated backlight pin.
+
+use embedded_hal::digital::v2::OutputPin;
+
+/// A pin (or no-op) that can be driven to control the panel backlight.
+pub trait BacklightPin {
+    /// Error type raised while toggling the pin.
+    type Error;
+
+    /// Turns the backlight on or off.
+    fn set_backlight(&mut self, on: bool) -> Result<(), Self::Error>;
+}
+
+impl BacklightPin for () {
+    type Error = core::convert::Infallible;
+
+    fn set_backlight(&mut self, _on: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Wraps a GPIO output pin so it can be used as a `BacklightPin`.
+pub struct Backlight<P>(pub P);
+
+impl<P: OutputPin> BacklightPin for Backlight<P> {
+    type Error = P::Error;
+
+    fn set_backlight(&mut self, on: bool) -> Result<(), Self::Error> {
+        if on {
+            self.0.set_high()
+        } else {
+            self.0.set_low()
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Abstraction over the hardware bus used to talk to the display
+//! controller, so the ST7735 command logic isn't tied to SPI.
+
+use embedded_hal::blocking::spi;
+use embedded_hal::digital::v2::OutputPin;
+
+/// A bus that can send commands and pixel data to the display controller.
+///
+/// Implementing this trait for a new bus (for example an MPU 8/9/16-bit
+/// parallel interface) is enough to drive the ST7735 controller logic
+/// without touching it.
+pub trait Interface {
+    /// Communication error type.
+    type Error;
+
+    /// Writes a command byte followed by its argument bytes.
+    fn write_command(&mut self, cmd: u8, args: &[u8]) -> Result<(), Self::Error>;
+
+    /// Writes a command byte followed by a stream of 16-bit data words.
+    fn write_iter(&mut self, cmd: u8, data: impl IntoIterator<Item = u16>) -> Result<(), Self::Error>;
+}
+
+/// Error raised by `SpiInterface`, wrapping either an SPI bus error or a
+/// data/command pin error.
+#[derive(Debug)]
+pub enum SpiInterfaceError<SPIE, DCE> {
+    Spi(SPIE),
+    Dc(DCE),
+}
+
+/// `Interface` implementation that drives the display over hardware SPI,
+/// using a GPIO data/command pin to select between command and data
+/// bytes.
+pub struct SpiInterface<SPI, DC> {
+    spi: SPI,
+    dc: DC,
+}
+
+impl<SPI, DC> SpiInterface<SPI, DC>
+where
+    SPI: spi::Write<u8>,
+    DC: OutputPin,
+{
+    /// Creates a new SPI interface wrapping the given bus and
+    /// data/command pin.
+    pub fn new(spi: SPI, dc: DC) -> Self {
+        SpiInterface { spi, dc }
+    }
+}
+
+impl<SPI, DC> Interface for SpiInterface<SPI, DC>
+where
+    SPI: spi::Write<u8>,
+    DC: OutputPin,
+{
+    type Error = SpiInterfaceError<SPI::Error, DC::Error>;
+
+    fn write_command(&mut self, cmd: u8, args: &[u8]) -> Result<(), Self::Error> {
+        self.dc.set_low().map_err(SpiInterfaceError::Dc)?;
+        self.spi.write(&[cmd]).map_err(SpiInterfaceError::Spi)?;
+        if !args.is_empty() {
+            self.dc.set_high().map_err(SpiInterfaceError::Dc)?;
+            self.spi.write(args).map_err(SpiInterfaceError::Spi)?;
+        }
+        Ok(())
+    }
+
+    fn write_iter(&mut self, cmd: u8, data: impl IntoIterator<Item = u16>) -> Result<(), Self::Error> {
+        self.dc.set_low().map_err(SpiInterfaceError::Dc)?;
+        self.spi.write(&[cmd]).map_err(SpiInterfaceError::Spi)?;
+        self.dc.set_high().map_err(SpiInterfaceError::Dc)?;
+        for word in data {
+            self.spi.write(&word.to_be_bytes()).map_err(SpiInterfaceError::Spi)?;
+        }
+        Ok(())
+    }
+}
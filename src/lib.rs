@@ -2,11 +2,15 @@
 
 //! This crate provides a ST7735 driver to connect to TFT displays.
 
+pub mod backlight;
 pub mod instruction;
+pub mod interface;
+#[cfg(feature = "graphics")]
+pub mod palette;
 
-use core::mem::transmute;
-
+use crate::backlight::BacklightPin;
 use crate::instruction::Instruction;
+use crate::interface::{Interface, SpiInterface};
 use num_traits::ToPrimitive;
 use num_derive::ToPrimitive;
 
@@ -14,22 +18,42 @@ use embedded_hal::digital::v2::OutputPin;
 use embedded_hal::blocking::spi;
 use embedded_hal::blocking::delay::DelayMs;
 
+#[cfg(feature = "graphics")]
+extern crate embedded_graphics_core;
+#[cfg(feature = "graphics")]
+use self::embedded_graphics_core::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, OriginDimensions, Size},
+    pixelcolor::{raw::RawU16, Rgb565, RgbColor},
+    primitives::Rectangle,
+    Pixel,
+};
+#[cfg(feature = "graphics")]
+use crate::palette::Palette;
+
+/// Converts an `Rgb565` color into the raw 16-bit word the controller
+/// expects over the wire.
+#[cfg(feature = "graphics")]
+fn color_to_word(color: Rgb565) -> u16 {
+    RawU16::from(color).into_inner()
+}
+
 /// ST7735 driver to connect to TFT displays.
-pub struct ST7735 <SPI, DC, RST>
+pub struct ST7735 <IFACE, RST, BL = ()>
 where
-    SPI: spi::Write<u8>,
-    DC: OutputPin,
+    IFACE: Interface,
     RST: OutputPin,
+    BL: BacklightPin,
 {
-    /// SPI
-    spi: SPI,
-
-    /// Data/command pin.
-    dc: DC,
+    /// Display interface.
+    iface: IFACE,
 
     /// Reset pin.
     rst: RST,
 
+    /// Backlight pin, or `()` if the panel has none under driver control.
+    bl: BL,
+
     /// Whether the display is RGB (true) or BGR (false)
     rgb: bool,
 
@@ -39,6 +63,26 @@ where
     /// Global image offset
     dx: u16,
     dy: u16,
+
+    /// Panel width/height in pixels for the current orientation, swapped
+    /// between the 160x80 landscape and 80x160 portrait dimensions by
+    /// `set_orientation`.
+    width: u16,
+    height: u16,
+}
+
+/// Driver error, distinguishing a display interface (comm) error from a
+/// reset pin (GPIO) error.
+#[derive(Debug)]
+pub enum Error<CommE, PinE> {
+    Comm(CommE),
+    Pin(PinE),
+}
+
+impl<CommE, PinE> From<CommE> for Error<CommE, PinE> {
+    fn from(err: CommE) -> Self {
+        Error::Comm(err)
+    }
 }
 
 /// Display orientation.
@@ -50,7 +94,7 @@ pub enum Orientation {
     LandscapeSwapped = 0xA0,
 }
 
-impl<SPI, DC, RST> ST7735<SPI, DC, RST>
+impl<SPI, DC, RST> ST7735<SpiInterface<SPI, DC>, RST>
 where
     SPI: spi::Write<u8>,
     DC: OutputPin,
@@ -65,21 +109,58 @@ where
         inverted: bool,
     ) -> Self
     {
-        let display = ST7735 {
-            spi,
-            dc,
+        Self::new_with_interface(SpiInterface::new(spi, dc), rst, rgb, inverted)
+    }
+}
+
+impl<IFACE, RST> ST7735<IFACE, RST>
+where
+    IFACE: Interface,
+    RST: OutputPin,
+{
+    /// Creates a new driver instance from an arbitrary `Interface`, e.g.
+    /// a parallel bus instead of SPI.
+    pub fn new_with_interface(
+        iface: IFACE,
+        rst: RST,
+        rgb: bool,
+        inverted: bool,
+    ) -> Self
+    {
+        Self::new_with_backlight(iface, rst, (), rgb, inverted)
+    }
+}
+
+impl<IFACE, RST, BL> ST7735<IFACE, RST, BL>
+where
+    IFACE: Interface,
+    RST: OutputPin,
+    BL: BacklightPin,
+{
+    /// Creates a new driver instance that also drives a backlight pin.
+    pub fn new_with_backlight(
+        iface: IFACE,
+        rst: RST,
+        bl: BL,
+        rgb: bool,
+        inverted: bool,
+    ) -> Self
+    {
+        ST7735 {
+            iface,
             rst,
+            bl,
             rgb,
             inverted,
             dx: 0,
-            dy: 0
-        };
-
-        display
+            dy: 0,
+            width: 160,
+            height: 80,
+        }
     }
 
     /// Runs commands to initialize the display.
-    pub fn init<DELAY>(&mut self, delay: &mut DELAY) -> Result<(), ()>
+    pub fn init<DELAY>(&mut self, delay: &mut DELAY) -> Result<(), Error<IFACE::Error, RST::Error>>
         where DELAY: DelayMs<u8>
     {
         self.hard_reset()?;
@@ -114,34 +195,19 @@ where
         Ok(())
     }
 
-    pub fn hard_reset(&mut self) -> Result<(), ()>
+    pub fn hard_reset(&mut self) -> Result<(), Error<IFACE::Error, RST::Error>>
     {
-        self.rst.set_high().map_err(|_| ())?;
-        self.rst.set_low().map_err(|_| ())?;
-        self.rst.set_high().map_err(|_| ())
+        self.rst.set_high().map_err(Error::Pin)?;
+        self.rst.set_low().map_err(Error::Pin)?;
+        self.rst.set_high().map_err(Error::Pin)
     }
 
-    fn write_command(&mut self, command: Instruction, params: Option<&[u8]>) -> Result<(), ()> {
-        self.dc.set_low().map_err(|_| ())?;
-        self.spi.write(&[command.to_u8().unwrap()]).map_err(|_| ())?;
-        if params.is_some() {
-            self.write_data(params.unwrap())?;
-        }
+    fn write_command(&mut self, command: Instruction, params: Option<&[u8]>) -> Result<(), Error<IFACE::Error, RST::Error>> {
+        self.iface.write_command(command.to_u8().unwrap(), params.unwrap_or(&[]))?;
         Ok(())
     }
 
-    fn write_data(&mut self, data: &[u8]) -> Result<(), ()> {
-        self.dc.set_high().map_err(|_| ())?;
-        self.spi.write(data).map_err(|_| ())
-    }
-
-    /// Writes a data word to the display.
-    fn write_word(&mut self, value: u16) -> Result<(), ()> {
-        let bytes: [u8; 2] = unsafe { transmute(value.to_be()) };
-        self.write_data(&bytes)
-    }
-
-    pub fn set_orientation(&mut self, orientation: &Orientation) -> Result<(), ()> {
+    pub fn set_orientation(&mut self, orientation: &Orientation) -> Result<(), Error<IFACE::Error, RST::Error>> {
         if self.rgb {
             self.write_command(
                 Instruction::MADCTL, Some(&[orientation.to_u8().unwrap()]
@@ -151,6 +217,16 @@ where
                 Instruction::MADCTL, Some(&[orientation.to_u8().unwrap() | 0x08 ]
             ))?;
         }
+        match orientation {
+            Orientation::Portrait | Orientation::PortraitSwapped => {
+                self.width = 80;
+                self.height = 160;
+            }
+            Orientation::Landscape | Orientation::LandscapeSwapped => {
+                self.width = 160;
+                self.height = 80;
+            }
+        }
         Ok(())
     }
 
@@ -161,60 +237,112 @@ where
     }
 
     /// Sets the address window for the display.
-    fn set_address_window(&mut self, sx: u16, sy: u16, ex: u16, ey: u16) -> Result<(), ()> {
-        self.write_command(Instruction::CASET, None)?;
-        self.write_word(sx + self.dx)?;
-        self.write_word(ex + self.dx)?;
-        self.write_command(Instruction::RASET, None)?;
-        self.write_word(sy + self.dy)?;
-        self.write_word(ey + self.dy)
+    fn set_address_window(&mut self, sx: u16, sy: u16, ex: u16, ey: u16) -> Result<(), Error<IFACE::Error, RST::Error>> {
+        self.iface.write_iter(Instruction::CASET.to_u8().unwrap(), [sx + self.dx, ex + self.dx])?;
+        self.iface.write_iter(Instruction::RASET.to_u8().unwrap(), [sy + self.dy, ey + self.dy])?;
+        Ok(())
     }
 
     /// Sets a pixel color at the given coords.
-    pub fn set_pixel(&mut self, x: u16, y: u16, color: u16) -> Result <(), ()> {
+    pub fn set_pixel(&mut self, x: u16, y: u16, color: u16) -> Result <(), Error<IFACE::Error, RST::Error>> {
         self.set_address_window(x, y, x, y)?;
-        self.write_command(Instruction::RAMWR, None)?;
-        self.write_word(color)
+        self.iface.write_iter(Instruction::RAMWR.to_u8().unwrap(), Some(color))?;
+        Ok(())
     }
 
     /// Writes pixel colors sequentially into the current drawing window
-    pub fn write_pixels<P: IntoIterator<Item = u16>>(&mut self, colors: P) -> Result <(), ()> {
-        self.write_command(Instruction::RAMWR, None)?;
-        for color in colors {
-            self.write_word(color)?;
-        }
+    pub fn write_pixels<P: IntoIterator<Item = u16>>(&mut self, colors: P) -> Result <(), Error<IFACE::Error, RST::Error>> {
+        self.iface.write_iter(Instruction::RAMWR.to_u8().unwrap(), colors)?;
         Ok(())
     }
 
     /// Sets pixel colors at the given drawing window
-    pub fn set_pixels<P: IntoIterator<Item = u16>>(&mut self, sx: u16, sy: u16, ex: u16, ey: u16, colors: P) -> Result <(), ()> {
+    pub fn set_pixels<P: IntoIterator<Item = u16>>(&mut self, sx: u16, sy: u16, ex: u16, ey: u16, colors: P) -> Result <(), Error<IFACE::Error, RST::Error>> {
         self.set_address_window(sx, sy, ex, ey)?;
         self.write_pixels(colors)
     }
-}
 
-/*
-    impl<C> IntoIterator for BatchPixels<C>
+    /// Sets pixel colors at the given drawing window from a stream of
+    /// palette indices, looking each one up in `palette` before it goes
+    /// out over the wire. Lets callers keep a 1-byte-per-pixel indexed
+    /// framebuffer instead of a full 16-bit one. An index past the end
+    /// of the palette is drawn as black.
+    #[cfg(feature = "graphics")]
+    pub fn set_pixels_indexed<P: IntoIterator<Item = u8>>(
+        &mut self, sx: u16, sy: u16, ex: u16, ey: u16, palette: &Palette, indices: P,
+    ) -> Result<(), Error<IFACE::Error, RST::Error>> {
+        self.set_pixels(sx, sy, ex, ey, indices.into_iter().map(|index| {
+            color_to_word(palette.get(index).unwrap_or(Rgb565::BLACK))
+        }))
+    }
+
+    /// Fills the given drawing window with a single palette entry. An
+    /// index past the end of the palette is drawn as black.
+    #[cfg(feature = "graphics")]
+    pub fn fill_indexed(
+        &mut self, sx: u16, sy: u16, ex: u16, ey: u16, palette: &Palette, index: u8,
+    ) -> Result<(), Error<IFACE::Error, RST::Error>> {
+        let count = (ex - sx + 1) as usize * (ey - sy + 1) as usize;
+        let word = color_to_word(palette.get(index).unwrap_or(Rgb565::BLACK));
+        self.set_pixels(sx, sy, ex, ey, core::iter::repeat(word).take(count))
+    }
+
+    /// Draws an iterator of pixels by batching them into contiguous rows
+    /// and blocks, so that each block is flushed with a single
+    /// `set_pixels` address window instead of one CASET/RASET/RAMWR
+    /// sequence per pixel.
+    #[cfg(feature = "graphics")]
+    pub fn draw_batch<P>(&mut self, pixels: P) -> Result<(), Error<IFACE::Error, RST::Error>>
     where
-        C: PixelColor,
+        P: Iterator<Item = Pixel<Rgb565>>,
     {
-        type Item = Pixel<C>;
-        type IntoIter = RowIterator<C>;
-
-        fn into_iter(self) -> Self::IntoIter {
-            RowIterator {
-                top_left: self.top_left,
-                bottom_right: self.bottom_right,
-                style: self.style,
-                p: self.top_left,
-            }
+        for block in to_blocks(to_rows(pixels)) {
+            self.set_pixels(
+                block.x_left, block.y_top,
+                block.x_right, block.y_bottom,
+                block.colors.iter().flat_map(|row| row.iter().copied()),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Turns the backlight on or off.
+    pub fn set_backlight(&mut self, on: bool) -> Result<(), BL::Error> {
+        self.bl.set_backlight(on)
+    }
+
+    /// Turns the panel's own display output on or off, leaving the
+    /// controller otherwise powered and the backlight untouched.
+    pub fn set_display_on(&mut self, on: bool) -> Result<(), Error<IFACE::Error, RST::Error>> {
+        if on {
+            self.write_command(Instruction::DISPON, None)
+        } else {
+            self.write_command(Instruction::DISPOFF, None)
         }
     }
-*/
+
+    /// Puts the controller to sleep and turns off the backlight, so
+    /// battery-powered projects can blank the panel between updates.
+    pub fn sleep<DELAY: DelayMs<u8>>(&mut self, delay: &mut DELAY) -> Result<(), Error<IFACE::Error, RST::Error>> {
+        self.write_command(Instruction::SLPIN, None)?;
+        delay.delay_ms(120);
+        let _ = self.bl.set_backlight(false);
+        Ok(())
+    }
+
+    /// Wakes the controller from sleep and turns the backlight back on.
+    pub fn wake<DELAY: DelayMs<u8>>(&mut self, delay: &mut DELAY) -> Result<(), Error<IFACE::Error, RST::Error>> {
+        self.write_command(Instruction::SLPOUT, None)?;
+        delay.delay_ms(120);
+        let _ = self.bl.set_backlight(true);
+        Ok(())
+    }
+}
 
 //////////////////////////////////////////////////////////
 
 /// Batch the pixels into rows
+#[cfg(feature = "graphics")]
 fn to_rows<P>(pixels: P) -> RowIterator<P>
 where
     P: Iterator<Item = Pixel<Rgb565>>, {
@@ -229,6 +357,7 @@ where
 }
 
 /// Batch the rows into blocks, which are contiguous rows
+#[cfg(feature = "graphics")]
 fn to_blocks<R>(rows: R) -> BlockIterator<R>
 where
     R: Iterator<Item = PixelRow>, {
@@ -244,16 +373,21 @@ where
 }    
 
 /// Max number of pixels per row
+#[cfg(feature = "graphics")]
 type MaxRowSize = heapless::consts::U240;
 /// Max number of rows per block
+#[cfg(feature = "graphics")]
 type MaxBlockSize = heapless::consts::U10;
 
 /// Consecutive color words for a row
+#[cfg(feature = "graphics")]
 type RowColors = heapless::Vec::<u16, MaxRowSize>;
 /// Consecutive color rows for a block
+#[cfg(feature = "graphics")]
 type BlockColors = heapless::Vec::<RowColors, MaxBlockSize>;
 
 /// Iterator for each row in the pixel data
+#[cfg(feature = "graphics")]
 #[derive(Debug, Clone)]
 pub struct RowIterator<P: Iterator<Item = Pixel<Rgb565>>> {
     pixels:      P,
@@ -265,6 +399,7 @@ pub struct RowIterator<P: Iterator<Item = Pixel<Rgb565>>> {
 }
 
 /// Iterator for each block in the pixel data
+#[cfg(feature = "graphics")]
 #[derive(Debug, Clone)]
 pub struct BlockIterator<R: Iterator<Item = PixelRow>> {
     rows:        R,
@@ -277,6 +412,7 @@ pub struct BlockIterator<R: Iterator<Item = PixelRow>> {
 }
 
 /// A row of contiguous pixels
+#[cfg(feature = "graphics")]
 pub struct PixelRow {
     pub x_left:  u16,
     pub x_right: u16,
@@ -285,6 +421,7 @@ pub struct PixelRow {
 }
 
 /// A block of contiguous row
+#[cfg(feature = "graphics")]
 pub struct PixelBlock {
     pub x_left:   u16,
     pub x_right:  u16,
@@ -293,6 +430,7 @@ pub struct PixelBlock {
     pub colors:   BlockColors,
 }
 
+#[cfg(feature = "graphics")]
 impl<P: Iterator<Item = Pixel<Rgb565>>> Iterator for RowIterator<P> {
     type Item = PixelRow;
 
@@ -315,9 +453,9 @@ impl<P: Iterator<Item = Pixel<Rgb565>>> Iterator for RowIterator<P> {
                     return Some(row);
                 }
                 Some(Pixel(coord, color)) => {
-                    let x = coord.0 as u16;
-                    let y = coord.1 as u16;
-                    let color = color.0;
+                    let x = coord.x as u16;
+                    let y = coord.y as u16;
+                    let color = color_to_word(color);
                     //  Save the first pixel as the row start and handle next pixel.
                     if self.first_pixel {
                         self.first_pixel = false;
@@ -329,14 +467,14 @@ impl<P: Iterator<Item = Pixel<Rgb565>>> Iterator for RowIterator<P> {
                             .expect("never");
                         continue;
                     }
-                    //  If this pixel is adjacent to the previous pixel, add to the row.
-                    if x == self.x_right + 1 && y == self.y {
-                        self.colors.push(color)
-                            .expect("row overflow");
+                    //  If this pixel is adjacent to the previous pixel and the row
+                    //  isn't full yet, add it to the row.
+                    if x == self.x_right + 1 && y == self.y && self.colors.push(color).is_ok() {
                         self.x_right = x;
                         continue;
                     }
-                    //  Else return previous pixels as row.
+                    //  Else return previous pixels as row (row is full, or this
+                    //  pixel starts a new run).
                     let row = PixelRow {
                         x_left: self.x_left,
                         x_right: self.x_right,
@@ -356,6 +494,7 @@ impl<P: Iterator<Item = Pixel<Rgb565>>> Iterator for RowIterator<P> {
     }
 }
 
+#[cfg(feature = "graphics")]
 impl<R: Iterator<Item = PixelRow>> Iterator for BlockIterator<R> {
     type Item = PixelBlock;
 
@@ -424,44 +563,76 @@ impl<R: Iterator<Item = PixelRow>> Iterator for BlockIterator<R> {
 ///////////////////////////////////
 
 #[cfg(feature = "graphics")]
-extern crate embedded_graphics;
-#[cfg(feature = "graphics")]
-use self::embedded_graphics::{drawable::{Pixel, Dimensions}, pixelcolor::Rgb565, Drawing, SizedDrawing};
-
-#[cfg(feature = "graphics")]
-impl<SPI, DC, RST> Drawing<Rgb565> for ST7735<SPI, DC, RST>
+impl<IFACE, RST, BL> OriginDimensions for ST7735<IFACE, RST, BL>
 where
-    SPI: spi::Write<u8>,
-    DC: OutputPin,
+    IFACE: Interface,
     RST: OutputPin,
+    BL: BacklightPin,
 {
-    fn draw<T>(&mut self, item_pixels: T)
-    where
-        T: IntoIterator<Item = Pixel<Rgb565>>,
-    {
-        for Pixel(coord, color) in item_pixels {
-            self.set_pixel(coord.0 as u16, coord.1 as u16, color.0).expect("pixel write failed");
-        }
+    /// Returns the panel dimensions for the orientation last set via
+    /// `set_orientation` (160x80 landscape by default).
+    fn size(&self) -> Size {
+        Size::new(self.width as u32, self.height as u32)
     }
 }
 
 #[cfg(feature = "graphics")]
-impl<SPI, DC, RST> SizedDrawing<Rgb565> for ST7735<SPI, DC, RST>
+impl<IFACE, RST, BL> DrawTarget for ST7735<IFACE, RST, BL>
 where
-    SPI: spi::Write<u8>,
-    DC: OutputPin,
+    IFACE: Interface,
     RST: OutputPin,
+    BL: BacklightPin,
 {
-    fn draw_sized<T>(&mut self, item_pixels: T)
+    type Color = Rgb565;
+    type Error = Error<IFACE::Error, RST::Error>;
+
+    /// Draws an arbitrary iterator of pixels, clipping any that fall
+    /// outside the panel instead of passing them on to the controller.
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
     where
-        T: IntoIterator<Item = Pixel<Rgb565>> + Dimensions,
+        I: IntoIterator<Item = Pixel<Self::Color>>,
     {
-        // Get bounding box `Coord`s as `(u32, u32)`
-        let top_left = item_pixels.top_left();
-        let bottom_right = item_pixels.bottom_right();
+        let bounds = self.bounding_box();
+        self.draw_batch(pixels.into_iter().filter(move |Pixel(point, _)| bounds.contains(*point)))
+    }
+
+    /// Streams the color iterator into the rectangle, dropping colors
+    /// whose point falls outside the panel instead of letting the extra
+    /// words wrap the address window and clobber already-drawn pixels.
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let bounds = self.bounding_box();
+        let pixels = area.points().zip(colors).filter_map(|(point, color)| {
+            if bounds.contains(point) {
+                Some(Pixel(point, color))
+            } else {
+                None
+            }
+        });
+        self.draw_batch(pixels)
+    }
+
+    /// Opens the window once and repeats the single color word across
+    /// the whole rectangle.
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        let bottom_right = match area.bottom_right() {
+            Some(point) => point,
+            None => return Ok(()),
+        };
+        let count = (area.size.width * area.size.height) as usize;
+        let word = color_to_word(color);
+        self.set_pixels(
+            area.top_left.x as u16, area.top_left.y as u16,
+            bottom_right.x as u16, bottom_right.y as u16,
+            core::iter::repeat(word).take(count),
+        )
+    }
 
-        self.set_pixels(top_left.0 as u16, top_left.1 as u16,
-                        bottom_right.0 as u16, bottom_right.1 as u16,
-                        item_pixels.into_iter().map(|Pixel(_coord, color)| color.0)).expect("pixels write failed")
+    /// Fills the whole framebuffer with one color.
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid(&self.bounding_box(), color)
     }
 }
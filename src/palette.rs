@@ -0,0 +1,72 @@
+//! Indexed/paletted color mode, for MCUs that can't hold a full 16-bit
+//! framebuffer. A `Palette` holds up to 256 `Rgb565` entries; callers
+//! keep a 1-byte-per-pixel index buffer and look colors up through it
+//! when streaming pixels to the panel.
+
+use embedded_graphics_core::pixelcolor::{raw::RawU16, Rgb565};
+use heapless::consts::U256;
+use heapless::Vec;
+
+/// Max number of entries in a `Palette` (one index byte per entry).
+type MaxPaletteSize = U256;
+
+/// A palette of up to 256 `Rgb565` colors.
+pub struct Palette {
+    colors: Vec<Rgb565, MaxPaletteSize>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Palette {
+    /// Creates an empty palette.
+    pub fn new() -> Self {
+        Palette { colors: Vec::new() }
+    }
+
+    /// Adds a color to the palette, returning its index. Fails if the
+    /// palette already holds 256 entries.
+    pub fn push(&mut self, color: Rgb565) -> Result<u8, Rgb565> {
+        let index = self.colors.len() as u8;
+        self.colors.push(color).map(|_| index)
+    }
+
+    /// Returns the color at `index`, or `None` if the palette doesn't
+    /// have that many entries.
+    pub fn get(&self, index: u8) -> Option<Rgb565> {
+        self.colors.get(index as usize).copied()
+    }
+
+    /// Finds the palette entry nearest to `color`, by summing squared
+    /// differences across the unpacked 5/6/5 channels. Returns `None` if
+    /// the palette is empty.
+    pub fn nearest(&self, color: Rgb565) -> Option<u8> {
+        let target = unpack565(RawU16::from(color).into_inner());
+        self.colors
+            .iter()
+            .map(|&c| unpack565(RawU16::from(c).into_inner()))
+            .enumerate()
+            .map(|(index, channels)| (index as u8, distance(target, channels)))
+            .min_by_key(|&(_, dist)| dist)
+            .map(|(index, _)| index)
+    }
+}
+
+/// Unpacks a raw RGB565 word into its `(r, g, b)` channels.
+fn unpack565(value: u16) -> (u16, u16, u16) {
+    let r = (value >> 11) & 0x1F;
+    let g = (value >> 5) & 0x3F;
+    let b = value & 0x1F;
+    (r, g, b)
+}
+
+/// Sum of squared channel differences between two unpacked colors.
+fn distance(a: (u16, u16, u16), b: (u16, u16, u16)) -> u32 {
+    let dr = a.0 as i32 - b.0 as i32;
+    let dg = a.1 as i32 - b.1 as i32;
+    let db = a.2 as i32 - b.2 as i32;
+    (dr * dr + dg * dg + db * db) as u32
+}